@@ -0,0 +1,269 @@
+use async_trait::async_trait;
+use reqwest::{header, Client, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::CloudflareConfig,
+    consul,
+    dns_trait::{DnsProviderTrait, DnsRecord, DnsRecordCreate},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CloudflareRecord {
+    id: String,
+    #[serde(rename = "type")]
+    type_: String,
+    name: String,
+    content: String,
+    ttl: u32,
+}
+
+impl From<CloudflareRecord> for DnsRecord {
+    fn from(record: CloudflareRecord) -> Self {
+        DnsRecord {
+            id: record.id,
+            zone_id: String::new(),
+            type_: record.type_,
+            name: record.name,
+            value: record.content,
+            ttl: record.ttl,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CloudflareRecordCreate {
+    #[serde(rename = "type")]
+    type_: String,
+    name: String,
+    content: String,
+    ttl: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordsResponse {
+    result: Vec<CloudflareRecord>,
+    result_info: ResultInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResultInfo {
+    page: u32,
+    total_pages: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordResponse {
+    result: CloudflareRecord,
+}
+
+pub struct CloudflareDns {
+    pub config: CloudflareConfig,
+    client: Client,
+}
+
+impl CloudflareDns {
+    pub fn new(config: CloudflareConfig) -> Result<Self, anyhow::Error> {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            header::HeaderValue::from_str(&format!("Bearer {}", config.api_token))?,
+        );
+
+        let client = Client::builder().default_headers(headers).build()?;
+        Ok(CloudflareDns { config, client })
+    }
+}
+
+#[async_trait]
+impl DnsProviderTrait for CloudflareDns {
+    /// Update or create a DNS record based on the Consul service tags.
+    /// If the record already exists, it will be updated if the value or ttl is different.
+    /// If the record does not exist, it will be created.
+    async fn update_or_create_dns_record<'a>(
+        &self,
+        dns_record: &'a consul::DnsRecord,
+    ) -> Result<DnsRecord, anyhow::Error> {
+        let existing_records = list_dns_records(&self.client, &self.config).await?;
+
+        let matched_record = existing_records
+            .iter()
+            .find(|record| record.name == dns_record.hostname && record.type_ == dns_record.type_);
+
+        match matched_record {
+            Some(record) => {
+                if record.value != dns_record.value || record.ttl != dns_record.ttl {
+                    let updated_record = DnsRecord {
+                        id: record.id.clone(),
+                        zone_id: self.config.zone_id.clone(),
+                        type_: dns_record.type_.clone(),
+                        name: dns_record.hostname.clone(),
+                        value: dns_record.value.clone(),
+                        ttl: dns_record.ttl,
+                    };
+                    let updated_record =
+                        update_dns_record(&self.client, &self.config, &updated_record).await?;
+                    Ok(updated_record)
+                } else {
+                    Ok(record.clone())
+                }
+            }
+            None => {
+                let new_record = DnsRecordCreate {
+                    zone_id: self.config.zone_id.clone(),
+                    type_: dns_record.type_.clone(),
+                    name: dns_record.hostname.clone(),
+                    value: dns_record.value.clone(),
+                    ttl: dns_record.ttl,
+                };
+                let created_record = create_dns_record(&self.client, &self.config, &new_record).await?;
+                Ok(created_record)
+            }
+        }
+    }
+
+    async fn delete_dns_record<'a>(&self, record_id: &'a str) -> Result<(), anyhow::Error> {
+        let url = format!(
+            "{}/zones/{}/dns_records/{}",
+            &self.config.api_url, &self.config.zone_id, record_id
+        );
+        self.client.delete(url).send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Reconcile `desired` against one `list_dns_records` snapshot of the
+    /// zone, creating/updating/deleting records via Cloudflare's per-record
+    /// PUT/POST/DELETE endpoints.
+    async fn reconcile(&self, desired: &[consul::DnsRecord]) -> Result<(), anyhow::Error> {
+        let existing_records = list_dns_records(&self.client, &self.config).await?;
+
+        for dns_record in desired {
+            let matched_record = existing_records
+                .iter()
+                .find(|record| record.name == dns_record.hostname && record.type_ == dns_record.type_);
+
+            match matched_record {
+                Some(record) if record.value != dns_record.value || record.ttl != dns_record.ttl => {
+                    let updated_record = DnsRecord {
+                        id: record.id.clone(),
+                        zone_id: self.config.zone_id.clone(),
+                        type_: dns_record.type_.clone(),
+                        name: dns_record.hostname.clone(),
+                        value: dns_record.value.clone(),
+                        ttl: dns_record.ttl,
+                    };
+                    update_dns_record(&self.client, &self.config, &updated_record).await?;
+                }
+                Some(_) => {}
+                None => {
+                    let new_record = DnsRecordCreate {
+                        zone_id: self.config.zone_id.clone(),
+                        type_: dns_record.type_.clone(),
+                        name: dns_record.hostname.clone(),
+                        value: dns_record.value.clone(),
+                        ttl: dns_record.ttl,
+                    };
+                    create_dns_record(&self.client, &self.config, &new_record).await?;
+                }
+            }
+        }
+
+        let stale_records = existing_records.iter().filter(|record| {
+            !desired
+                .iter()
+                .any(|dns_record| record.name == dns_record.hostname && record.type_ == dns_record.type_)
+        });
+
+        for record in stale_records {
+            self.delete_dns_record(&record.id).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Cloudflare paginates `dns_records` at 100 results per page by default.
+/// Page through `result_info` until every page has been fetched, since a
+/// partial listing would make records past page 1 look absent and get
+/// recreated as duplicates on the next reconcile.
+async fn list_dns_records(
+    client: &Client,
+    config: &CloudflareConfig,
+) -> Result<Vec<DnsRecord>, Error> {
+    let mut records = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let url = format!(
+            "{}/zones/{}/dns_records?page={}&per_page=100",
+            &config.api_url, &config.zone_id, page
+        );
+        let response = client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<RecordsResponse>()
+            .await?;
+
+        records.extend(response.result.into_iter().map(DnsRecord::from));
+
+        if response.result_info.page >= response.result_info.total_pages {
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(records)
+}
+
+async fn update_dns_record(
+    client: &Client,
+    config: &CloudflareConfig,
+    record: &DnsRecord,
+) -> Result<DnsRecord, Error> {
+    let url = format!(
+        "{}/zones/{}/dns_records/{}",
+        &config.api_url, &config.zone_id, &record.id
+    );
+    let body = CloudflareRecordCreate {
+        type_: record.type_.clone(),
+        name: record.name.clone(),
+        content: record.value.clone(),
+        ttl: record.ttl,
+    };
+    let response = client
+        .put(url)
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<RecordResponse>()
+        .await?;
+
+    Ok(response.result.into())
+}
+
+async fn create_dns_record(
+    client: &Client,
+    config: &CloudflareConfig,
+    record_create: &DnsRecordCreate,
+) -> Result<DnsRecord, Error> {
+    let url = format!("{}/zones/{}/dns_records", &config.api_url, &config.zone_id);
+    let body = CloudflareRecordCreate {
+        type_: record_create.type_.clone(),
+        name: record_create.name.clone(),
+        content: record_create.value.clone(),
+        ttl: record_create.ttl,
+    };
+    let response = client
+        .post(url)
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<RecordResponse>()
+        .await?;
+
+    Ok(response.result.into())
+}
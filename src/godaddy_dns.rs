@@ -0,0 +1,187 @@
+use async_trait::async_trait;
+use reqwest::{header, Client, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::GoDaddyConfig,
+    consul,
+    dns_trait::{DnsProviderTrait, DnsRecord},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GoDaddyRecord {
+    #[serde(rename = "type")]
+    type_: String,
+    name: String,
+    data: String,
+    ttl: u32,
+}
+
+pub struct GoDaddyDns {
+    pub config: GoDaddyConfig,
+    client: Client,
+}
+
+impl GoDaddyDns {
+    pub fn new(config: GoDaddyConfig) -> Result<Self, anyhow::Error> {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            header::HeaderValue::from_str(&format!("sso-key {}:{}", config.api_key, config.api_secret))?,
+        );
+
+        let client = Client::builder().default_headers(headers).build()?;
+        Ok(GoDaddyDns { config, client })
+    }
+}
+
+#[async_trait]
+impl DnsProviderTrait for GoDaddyDns {
+    /// Update or create a DNS record based on the Consul service tags.
+    /// If the record already exists, it will be updated if the value or ttl is different.
+    /// If the record does not exist, it will be created.
+    async fn update_or_create_dns_record<'a>(
+        &self,
+        dns_record: &'a consul::DnsRecord,
+    ) -> Result<DnsRecord, anyhow::Error> {
+        let existing_records = list_dns_records(&self.client, &self.config).await?;
+
+        let matched_record = existing_records
+            .iter()
+            .find(|record| record.name == dns_record.hostname && record.type_ == dns_record.type_);
+
+        match matched_record {
+            Some(record) if record.value != dns_record.value || record.ttl != dns_record.ttl => {
+                put_record(&self.client, &self.config, &dns_record.hostname, &dns_record.type_, dns_record.ttl, &dns_record.value)
+                    .await?;
+            }
+            Some(record) => return Ok(record.clone()),
+            None => {
+                put_record(&self.client, &self.config, &dns_record.hostname, &dns_record.type_, dns_record.ttl, &dns_record.value)
+                    .await?;
+            }
+        }
+
+        Ok(DnsRecord {
+            id: encode_id(&self.config.domain, &dns_record.type_, &dns_record.hostname),
+            zone_id: self.config.domain.clone(),
+            type_: dns_record.type_.clone(),
+            name: dns_record.hostname.clone(),
+            value: dns_record.value.clone(),
+            ttl: dns_record.ttl,
+        })
+    }
+
+    /// Delete a DNS record. GoDaddy has no per-record id, so `record_id` is
+    /// expected to be the `domain|type|name` id produced by [`encode_id`].
+    async fn delete_dns_record<'a>(&self, record_id: &'a str) -> Result<(), anyhow::Error> {
+        let (domain, type_, name) = decode_id(record_id)?;
+        let url = format!(
+            "{}/v1/domains/{}/records/{}/{}",
+            &self.config.api_url, domain, type_, name
+        );
+        self.client.delete(url).send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// Reconcile `desired` against one `list_dns_records` snapshot of the
+    /// domain. GoDaddy's PUT replaces the whole record set for a name+type,
+    /// so creates and updates both just call `put_record`.
+    async fn reconcile(&self, desired: &[consul::DnsRecord]) -> Result<(), anyhow::Error> {
+        let existing_records = list_dns_records(&self.client, &self.config).await?;
+
+        for dns_record in desired {
+            let matched_record = existing_records
+                .iter()
+                .find(|record| record.name == dns_record.hostname && record.type_ == dns_record.type_);
+
+            let needs_write = match matched_record {
+                Some(record) => record.value != dns_record.value || record.ttl != dns_record.ttl,
+                None => true,
+            };
+
+            if needs_write {
+                put_record(
+                    &self.client,
+                    &self.config,
+                    &dns_record.hostname,
+                    &dns_record.type_,
+                    dns_record.ttl,
+                    &dns_record.value,
+                )
+                .await?;
+            }
+        }
+
+        let stale_records = existing_records.iter().filter(|record| {
+            !desired
+                .iter()
+                .any(|dns_record| record.name == dns_record.hostname && record.type_ == dns_record.type_)
+        });
+
+        for record in stale_records {
+            self.delete_dns_record(&record.id).await?;
+        }
+
+        Ok(())
+    }
+}
+
+async fn list_dns_records(client: &Client, config: &GoDaddyConfig) -> Result<Vec<DnsRecord>, Error> {
+    let url = format!("{}/v1/domains/{}/records", &config.api_url, &config.domain);
+    let records = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<GoDaddyRecord>>()
+        .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| DnsRecord {
+            id: encode_id(&config.domain, &record.type_, &record.name),
+            zone_id: config.domain.clone(),
+            type_: record.type_,
+            name: record.name,
+            value: record.data,
+            ttl: record.ttl,
+        })
+        .collect())
+}
+
+async fn put_record(
+    client: &Client,
+    config: &GoDaddyConfig,
+    name: &str,
+    type_: &str,
+    ttl: u32,
+    value: &str,
+) -> Result<(), Error> {
+    let url = format!(
+        "{}/v1/domains/{}/records/{}/{}",
+        &config.api_url, &config.domain, type_, name
+    );
+    let body = vec![GoDaddyRecord {
+        type_: type_.to_string(),
+        name: name.to_string(),
+        data: value.to_string(),
+        ttl,
+    }];
+
+    client.put(url).json(&body).send().await?.error_for_status()?;
+    Ok(())
+}
+
+/// Encode `domain|type|name` as the record id, since GoDaddy has no numeric id.
+fn encode_id(domain: &str, type_: &str, name: &str) -> String {
+    format!("{}|{}|{}", domain, type_, name)
+}
+
+fn decode_id(id: &str) -> Result<(String, String, String), anyhow::Error> {
+    let mut parts = id.splitn(3, '|');
+    let domain = parts.next().ok_or_else(|| anyhow::anyhow!("invalid record id: {}", id))?;
+    let type_ = parts.next().ok_or_else(|| anyhow::anyhow!("invalid record id: {}", id))?;
+    let name = parts.next().ok_or_else(|| anyhow::anyhow!("invalid record id: {}", id))?;
+    Ok((domain.to_string(), type_.to_string(), name.to_string()))
+}
@@ -1,11 +1,16 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
 use async_trait::async_trait;
-use reqwest::{header, Client, Error};
+use reqwest::{header, Client, Error, StatusCode};
 use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
 
 use crate::{
     config::HetznerConfig,
     consul,
     dns_trait::{DnsProviderTrait, DnsRecord, DnsRecordCreate},
+    record_cache::RecordCache,
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,8 +23,95 @@ struct RecordResponse {
     record: DnsRecord,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Zone {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ZonesWrapper {
+    zones: Vec<Zone>,
+}
+
+/// Exponential backoff policy used when the Hetzner DNS API responds with
+/// 429 or a 5xx status. `max_attempts` caps the total number of tries
+/// (including the first), and `base_delay` is doubled on every retry (up to
+/// 64x) and jittered, unless the response carries a `Retry-After` header.
+#[derive(Debug, Clone)]
+struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+}
+
+impl RetryPolicy {
+    fn from_config(config: &HetznerConfig) -> Self {
+        RetryPolicy {
+            max_attempts: config.retry_max_attempts,
+            base_delay: Duration::from_millis(config.retry_base_delay_ms),
+        }
+    }
+}
+
 pub struct HetznerDns {
     pub config: HetznerConfig,
+    client: Client,
+    retry_policy: RetryPolicy,
+    zones: OnceCell<Vec<Zone>>,
+    cache: RecordCache,
+}
+
+impl HetznerDns {
+    pub fn new(config: HetznerConfig) -> Result<Self, anyhow::Error> {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(
+            "Auth-API-Token",
+            header::HeaderValue::from_str(&config.dns_token)?,
+        );
+
+        let client = Client::builder().default_headers(headers).build()?;
+        let retry_policy = RetryPolicy::from_config(&config);
+        let cache = RecordCache::load(
+            config.cache_path.clone(),
+            Duration::from_secs(config.cache_ttl_secs),
+        );
+
+        Ok(HetznerDns {
+            config,
+            client,
+            retry_policy,
+            zones: OnceCell::new(),
+            cache,
+        })
+    }
+
+    async fn zones(&self) -> Result<&Vec<Zone>, anyhow::Error> {
+        self.zones
+            .get_or_try_init(|| list_zones(&self.client, &self.config, &self.retry_policy))
+            .await
+    }
+
+    /// Resolve the zone that should own `hostname` by picking the configured
+    /// zone whose apex is the longest suffix of `hostname`.
+    async fn resolve_zone_id(&self, hostname: &str) -> Result<String, anyhow::Error> {
+        let zones = self.zones().await?;
+
+        longest_suffix_zone(zones, hostname)
+            .map(|zone| zone.id.clone())
+            .ok_or_else(|| anyhow::anyhow!("no configured zone covers hostname {}", hostname))
+    }
+
+    /// Return the cached snapshot for `zone_id` if it's still fresh,
+    /// otherwise re-list the zone and refresh the cache.
+    async fn cached_records(&self, zone_id: &str) -> Result<Vec<DnsRecord>, anyhow::Error> {
+        if let Some(records) = self.cache.get(zone_id) {
+            return Ok(records);
+        }
+
+        let records = list_dns_records(&self.client, &self.config, &self.retry_policy, zone_id).await?;
+        self.cache.put(zone_id, records.clone());
+        Ok(records)
+    }
 }
 
 #[async_trait]
@@ -31,11 +123,13 @@ impl DnsProviderTrait for HetznerDns {
         &self,
         dns_record: &'a consul::DnsRecord,
     ) -> Result<DnsRecord, anyhow::Error> {
-        let existing_records = match list_dns_records(&self.config).await {
+        let zone_id = self.resolve_zone_id(&dns_record.hostname).await?;
+
+        let existing_records = match self.cached_records(&zone_id).await {
             Ok(records) => records,
             Err(e) => {
                 eprintln!("Failed to list DNS records: {}", e);
-                return Err(e.into());
+                return Err(e);
             }
         };
 
@@ -55,7 +149,22 @@ impl DnsProviderTrait for HetznerDns {
                         value: dns_record.value.clone(),
                         ttl: dns_record.ttl,
                     };
-                    let updated_record = update_dns_record(&self.config, &updated_record).await?;
+                    let updated_record = match update_dns_record(
+                        &self.client,
+                        &self.config,
+                        &self.retry_policy,
+                        &updated_record,
+                    )
+                    .await
+                    {
+                        Ok(record) => record,
+                        Err(e) => {
+                            self.cache.invalidate(&zone_id);
+                            return Err(e.into());
+                        }
+                    };
+                    self.cache.upsert_record(&zone_id, updated_record.clone());
+                    self.cache.flush().await;
                     Ok(updated_record)
                 } else {
                     Ok(record.clone())
@@ -64,44 +173,197 @@ impl DnsProviderTrait for HetznerDns {
             None => {
                 // Create a new DNS record
                 let new_record = DnsRecordCreate {
-                    zone_id: self.config.dns_zone_id.clone(),
+                    zone_id: zone_id.clone(),
                     type_: dns_record.type_.clone(),
                     name: dns_record.hostname.clone(),
                     value: dns_record.value.clone(),
                     ttl: dns_record.ttl,
                 };
-                let created_record = create_dns_record(&self.config, &new_record).await?;
+                let created_record =
+                    match create_dns_record(&self.client, &self.config, &self.retry_policy, &new_record)
+                        .await
+                    {
+                        Ok(record) => record,
+                        Err(e) => {
+                            self.cache.invalidate(&zone_id);
+                            return Err(e.into());
+                        }
+                    };
+                self.cache.upsert_record(&zone_id, created_record.clone());
+                self.cache.flush().await;
                 Ok(created_record)
             }
         }
     }
 
     async fn delete_dns_record<'a>(&self, record_id: &'a str) -> Result<(), anyhow::Error> {
-        let url = format!("{}/records/{}", &self.config.api_url, record_id);
-        let client = Client::new();
-        client
-            .delete(url)
-            .header("Auth-API-Token", &self.config.dns_token)
-            .send()
-            .await?
-            .error_for_status()?;
+        delete_dns_record(&self.client, &self.config, &self.retry_policy, record_id).await?;
+        for zone in self.zones().await? {
+            self.cache.remove_record(&zone.id, record_id);
+        }
+        self.cache.flush().await;
+        Ok(())
+    }
+
+    /// Reconcile `desired` against one cached snapshot per zone (grouped by
+    /// longest-suffix zone match), creating/updating/deleting via Hetzner's
+    /// per-record REST endpoints instead of one list+diff per record.
+    async fn reconcile(&self, desired: &[consul::DnsRecord]) -> Result<(), anyhow::Error> {
+        let mut by_zone: HashMap<String, Vec<&consul::DnsRecord>> = HashMap::new();
+        for dns_record in desired {
+            let zone_id = self.resolve_zone_id(&dns_record.hostname).await?;
+            by_zone.entry(zone_id).or_default().push(dns_record);
+        }
+
+        for (zone_id, zone_desired) in by_zone {
+            let existing_records = self.cached_records(&zone_id).await?;
+
+            for dns_record in &zone_desired {
+                let matched_record = existing_records.iter().find(|record| {
+                    record.name == dns_record.hostname && record.type_ == dns_record.type_
+                });
+
+                match matched_record {
+                    Some(record)
+                        if record.value != dns_record.value || record.ttl != dns_record.ttl =>
+                    {
+                        let updated_record = DnsRecord {
+                            id: record.id.clone(),
+                            zone_id: record.zone_id.clone(),
+                            type_: dns_record.type_.clone(),
+                            name: dns_record.hostname.clone(),
+                            value: dns_record.value.clone(),
+                            ttl: dns_record.ttl,
+                        };
+                        match update_dns_record(&self.client, &self.config, &self.retry_policy, &updated_record)
+                            .await
+                        {
+                            Ok(record) => self.cache.upsert_record(&zone_id, record),
+                            Err(e) => {
+                                self.cache.invalidate(&zone_id);
+                                return Err(e.into());
+                            }
+                        }
+                    }
+                    Some(_) => {}
+                    None => {
+                        let new_record = DnsRecordCreate {
+                            zone_id: zone_id.clone(),
+                            type_: dns_record.type_.clone(),
+                            name: dns_record.hostname.clone(),
+                            value: dns_record.value.clone(),
+                            ttl: dns_record.ttl,
+                        };
+                        match create_dns_record(&self.client, &self.config, &self.retry_policy, &new_record)
+                            .await
+                        {
+                            Ok(record) => self.cache.upsert_record(&zone_id, record),
+                            Err(e) => {
+                                self.cache.invalidate(&zone_id);
+                                return Err(e.into());
+                            }
+                        }
+                    }
+                }
+            }
+
+            let stale_records = existing_records.iter().filter(|record| {
+                !zone_desired
+                    .iter()
+                    .any(|dns_record| record.name == dns_record.hostname && record.type_ == dns_record.type_)
+            });
+
+            for record in stale_records {
+                if let Err(e) =
+                    delete_dns_record(&self.client, &self.config, &self.retry_policy, &record.id).await
+                {
+                    self.cache.invalidate(&zone_id);
+                    return Err(e.into());
+                }
+                self.cache.remove_record(&zone_id, &record.id);
+            }
+        }
+
+        self.cache.flush().await;
         Ok(())
     }
 }
 
-async fn list_dns_records(hetzner_config: &HetznerConfig) -> Result<Vec<DnsRecord>, Error> {
-    let client = Client::new();
-    let mut headers = header::HeaderMap::new();
-    headers.insert(
-        "Auth-API-Token",
-        header::HeaderValue::from_str(&hetzner_config.dns_token).unwrap(),
-    );
+/// Send a request built fresh on every attempt, retrying on 429/5xx with
+/// exponential backoff and jitter, honoring `Retry-After` when present in its
+/// delay-seconds form (an HTTP-date value falls back to the backoff).
+async fn send_with_retry<F>(build: F, policy: &RetryPolicy) -> Result<reqwest::Response, Error>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let mut attempt = 1;
+    loop {
+        let response = build().send().await?;
+        let status = response.status();
 
-    let url = format!(
-        "{}/records?zone_id={}",
-        &hetzner_config.api_url, &hetzner_config.dns_zone_id
-    );
-    let response = client.get(url).headers(headers).send().await?;
+        if !(status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()) {
+            return Ok(response);
+        }
+        if attempt >= policy.max_attempts {
+            return response.error_for_status();
+        }
+
+        let delay = retry_after_delay(&response).unwrap_or_else(|| backoff_with_jitter(policy.base_delay, attempt));
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+fn retry_after_delay(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+fn backoff_with_jitter(base_delay: Duration, attempt: u32) -> Duration {
+    // Cap the exponent so a large operator-configured `retry_max_attempts`
+    // can't overflow (or wrap to a near-zero delay) the `2u64.pow` below.
+    let exponent = attempt.saturating_sub(1).min(6);
+    let exponential = base_delay.as_millis() as u64 * 2u64.pow(exponent);
+    let jitter = rand::random::<u64>() % (exponential / 2 + 1);
+    Duration::from_millis(exponential + jitter)
+}
+
+/// Pick the zone whose apex is the longest suffix of `hostname`, so e.g.
+/// `svc.prod.example.com` resolves to the `prod.example.com` zone over the
+/// `example.com` zone when both are configured.
+fn longest_suffix_zone<'a>(zones: &'a [Zone], hostname: &str) -> Option<&'a Zone> {
+    zones
+        .iter()
+        .filter(|zone| hostname == zone.name || hostname.ends_with(&format!(".{}", zone.name)))
+        .max_by_key(|zone| zone.name.len())
+}
+
+async fn list_zones(
+    client: &Client,
+    hetzner_config: &HetznerConfig,
+    retry_policy: &RetryPolicy,
+) -> Result<Vec<Zone>, anyhow::Error> {
+    let url = format!("{}/zones", &hetzner_config.api_url);
+    let response = send_with_retry(|| client.get(&url), retry_policy)
+        .await?
+        .error_for_status()?;
+
+    let zones = response.json::<ZonesWrapper>().await?;
+    Ok(zones.zones)
+}
+
+async fn list_dns_records(
+    client: &Client,
+    hetzner_config: &HetznerConfig,
+    retry_policy: &RetryPolicy,
+    zone_id: &str,
+) -> Result<Vec<DnsRecord>, Error> {
+    let url = format!("{}/records?zone_id={}", &hetzner_config.api_url, zone_id);
+    let response = send_with_retry(|| client.get(&url), retry_policy).await?;
 
     match response.error_for_status() {
         Ok(res) => {
@@ -113,36 +375,96 @@ async fn list_dns_records(hetzner_config: &HetznerConfig) -> Result<Vec<DnsRecor
 }
 
 async fn update_dns_record(
+    client: &Client,
     hetzner_config: &HetznerConfig,
+    retry_policy: &RetryPolicy,
     record: &DnsRecord,
 ) -> Result<DnsRecord, Error> {
-    let client = Client::new();
     let url = format!("{}/records/{}", &hetzner_config.api_url, &record.id);
-    let res = client
-        .put(url)
-        .header("Auth-API-Token", &hetzner_config.dns_token)
-        .json(record)
-        .send()
-        .await?;
+    let res = send_with_retry(|| client.put(&url).json(record), retry_policy)
+        .await?
+        .error_for_status()?;
 
     let updated_dns = res.json::<RecordResponse>().await?;
     Ok(updated_dns.record)
 }
 
 async fn create_dns_record(
+    client: &Client,
     hetzner_config: &HetznerConfig,
+    retry_policy: &RetryPolicy,
     record_create: &DnsRecordCreate,
 ) -> Result<DnsRecord, Error> {
-    let client = Client::new();
     let url = format!("{}/records", &hetzner_config.api_url);
-    let res = client
-        .post(url)
-        .header("Auth-API-Token", &hetzner_config.dns_token)
-        .json(record_create)
-        .send()
+    let res = send_with_retry(|| client.post(&url).json(record_create), retry_policy)
         .await?
         .error_for_status()?;
 
     let created_dns = res.json::<RecordResponse>().await?;
     Ok(created_dns.record)
 }
+
+async fn delete_dns_record(
+    client: &Client,
+    hetzner_config: &HetznerConfig,
+    retry_policy: &RetryPolicy,
+    record_id: &str,
+) -> Result<(), Error> {
+    let url = format!("{}/records/{}", &hetzner_config.api_url, record_id);
+    send_with_retry(|| client.delete(&url), retry_policy)
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(id: &str, name: &str) -> Zone {
+        Zone {
+            id: id.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn longest_suffix_zone_prefers_the_more_specific_zone() {
+        let zones = vec![zone("z1", "example.com"), zone("z2", "prod.example.com")];
+
+        let matched = longest_suffix_zone(&zones, "svc.prod.example.com");
+        assert_eq!(matched.map(|z| z.id.as_str()), Some("z2"));
+    }
+
+    #[test]
+    fn longest_suffix_zone_falls_back_to_the_only_covering_zone() {
+        let zones = vec![zone("z1", "example.com"), zone("z2", "prod.example.com")];
+
+        let matched = longest_suffix_zone(&zones, "svc.example.com");
+        assert_eq!(matched.map(|z| z.id.as_str()), Some("z1"));
+    }
+
+    #[test]
+    fn longest_suffix_zone_matches_the_apex_itself() {
+        let zones = vec![zone("z1", "example.com")];
+
+        let matched = longest_suffix_zone(&zones, "example.com");
+        assert_eq!(matched.map(|z| z.id.as_str()), Some("z1"));
+    }
+
+    #[test]
+    fn longest_suffix_zone_rejects_unrelated_hostnames() {
+        let zones = vec![zone("z1", "example.com")];
+
+        // "notexample.com" ends with "example.com" as a raw string but isn't
+        // actually a subdomain of it, so it must not match.
+        assert!(longest_suffix_zone(&zones, "notexample.com").is_none());
+    }
+
+    #[test]
+    fn longest_suffix_zone_returns_none_when_nothing_covers_hostname() {
+        let zones = vec![zone("z1", "example.com")];
+
+        assert!(longest_suffix_zone(&zones, "example.net").is_none());
+    }
+}
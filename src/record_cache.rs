@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::dns_trait::DnsRecord;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedZone {
+    records: Vec<DnsRecord>,
+    fetched_at_secs: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    zones: HashMap<String, CachedZone>,
+}
+
+/// A per-zone cache of the last `list_dns_records` snapshot, so a reconcile
+/// only has to re-list a zone once the cache is older than `ttl` or after a
+/// write fails. Successful creates/updates/deletes mutate the cached copy
+/// directly instead of invalidating it. `put`/`invalidate` persist to `path`
+/// immediately since they're infrequent, but `upsert_record`/`remove_record`
+/// only mutate the in-memory copy — callers that mutate many records in a
+/// loop (e.g. `reconcile`) should call [`RecordCache::flush`] once afterwards
+/// rather than persisting after every record. Every write to `path`, whether
+/// from `persist` or `flush`, runs on a blocking task so it never stalls the
+/// executor.
+pub struct RecordCache {
+    path: PathBuf,
+    ttl: Duration,
+    zones: Mutex<HashMap<String, CachedZone>>,
+}
+
+impl RecordCache {
+    pub fn load(path: PathBuf, ttl: Duration) -> Self {
+        let zones = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<CacheFile>(&bytes).ok())
+            .map(|file| file.zones)
+            .unwrap_or_default();
+
+        RecordCache {
+            path,
+            ttl,
+            zones: Mutex::new(zones),
+        }
+    }
+
+    /// Return the cached records for `zone_id` if present and not older than `ttl`.
+    pub fn get(&self, zone_id: &str) -> Option<Vec<DnsRecord>> {
+        let zones = self.zones.lock().unwrap();
+        let cached = zones.get(zone_id)?;
+        if now_secs().saturating_sub(cached.fetched_at_secs) > self.ttl.as_secs() {
+            return None;
+        }
+        Some(cached.records.clone())
+    }
+
+    /// Replace the cached snapshot for `zone_id`, e.g. after a fresh `list_dns_records`.
+    pub fn put(&self, zone_id: &str, records: Vec<DnsRecord>) {
+        self.zones.lock().unwrap().insert(
+            zone_id.to_string(),
+            CachedZone {
+                records,
+                fetched_at_secs: now_secs(),
+            },
+        );
+        self.persist();
+    }
+
+    /// Drop the cached snapshot for `zone_id`, forcing the next read to re-list.
+    pub fn invalidate(&self, zone_id: &str) {
+        self.zones.lock().unwrap().remove(zone_id);
+        self.persist();
+    }
+
+    /// Insert or replace a single record in the cached snapshot after a
+    /// successful create/update, without forcing a full re-list. Does not
+    /// persist; call [`RecordCache::flush`] once the batch of mutations is done.
+    pub fn upsert_record(&self, zone_id: &str, record: DnsRecord) {
+        let mut zones = self.zones.lock().unwrap();
+        let cached = zones.entry(zone_id.to_string()).or_insert_with(|| CachedZone {
+            records: Vec::new(),
+            fetched_at_secs: now_secs(),
+        });
+
+        match cached.records.iter_mut().find(|r| r.id == record.id) {
+            Some(existing) => *existing = record,
+            None => cached.records.push(record),
+        }
+    }
+
+    /// Remove a single record from the cached snapshot after a successful
+    /// delete. Does not persist; call [`RecordCache::flush`] once the batch
+    /// of mutations is done.
+    pub fn remove_record(&self, zone_id: &str, record_id: &str) {
+        if let Some(cached) = self.zones.lock().unwrap().get_mut(zone_id) {
+            cached.records.retain(|r| r.id != record_id);
+        }
+    }
+
+    /// Write the current in-memory cache to disk. Cheap individual mutations
+    /// (`put`, `invalidate`) persist on their own, but batches of
+    /// `upsert_record`/`remove_record` calls (e.g. one per record in a
+    /// `reconcile`) should only call this once after the batch, and the
+    /// write itself runs on a blocking task so it doesn't stall the
+    /// executor.
+    pub async fn flush(&self) {
+        let file = CacheFile {
+            zones: self.zones.lock().unwrap().clone(),
+        };
+        let path = self.path.clone();
+
+        let _ = tokio::task::spawn_blocking(move || write_cache_file(&path, &file)).await;
+    }
+
+    fn persist(&self) {
+        let file = CacheFile {
+            zones: self.zones.lock().unwrap().clone(),
+        };
+        let path = self.path.clone();
+
+        let _ = tokio::task::spawn_blocking(move || write_cache_file(&path, &file));
+    }
+}
+
+fn write_cache_file(path: &std::path::Path, file: &CacheFile) {
+    let Ok(bytes) = serde_json::to_vec(file) else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let _ = fs::write(path, bytes);
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,511 @@
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use hickory_client::client::{AsyncClient, ClientHandle};
+use hickory_client::op::{DnsResponse, Message, MessageType, OpCode, Query, ResponseCode};
+use hickory_client::proto::iocompat::AsyncIoTokioAsStd;
+use hickory_client::proto::rr::rdata::tsig::TsigAlgorithm;
+use hickory_client::proto::rr::{DNSClass, Name, RData, Record, RecordType};
+use hickory_client::proto::xfer::{DnsRequest, DnsRequestOptions};
+use hickory_client::tcp::TcpClientStream;
+use hickory_client::tsig::TSigner;
+use tokio::net::TcpStream;
+
+use crate::{
+    config::Rfc2136Config,
+    consul,
+    dns_trait::{DnsProviderTrait, DnsRecord},
+};
+
+/// DNS dynamic-update provider (RFC 2136) backed by `hickory-client`.
+///
+/// Unlike the HTTP-based providers this one talks directly to an
+/// authoritative server (Knot, BIND, PowerDNS, ...) and authenticates
+/// updates with TSIG instead of an API token.
+pub struct Rfc2136Dns {
+    pub config: Rfc2136Config,
+}
+
+impl Rfc2136Dns {
+    fn signer(&self) -> Result<TSigner, anyhow::Error> {
+        let algorithm = match self.config.tsig_algorithm.as_str() {
+            "hmac-sha256" => TsigAlgorithm::HmacSha256,
+            "hmac-sha512" => TsigAlgorithm::HmacSha512,
+            other => return Err(anyhow::anyhow!("unsupported TSIG algorithm: {}", other)),
+        };
+
+        Ok(TSigner::new(
+            self.config.tsig_secret.clone(),
+            algorithm,
+            Name::from_str(&self.config.tsig_key_name)?,
+            300,
+        )?)
+    }
+
+    async fn connect(&self) -> Result<AsyncClient, anyhow::Error> {
+        let addr: SocketAddr = self.config.server.parse()?;
+        let (stream, sender) = TcpClientStream::<AsyncIoTokioAsStd<TcpStream>>::new(addr);
+        let signer = self.signer()?;
+        let (client, bg) = AsyncClient::with_signer(stream, sender, Some(Arc::new(signer))).await?;
+        tokio::spawn(bg);
+        Ok(client)
+    }
+
+    fn apex(&self) -> Result<Name, anyhow::Error> {
+        Ok(Name::from_str(&self.config.zone)?.append_domain(&Name::root())?)
+    }
+
+    /// List every record in the zone via AXFR, skipping the enclosing SOA pair.
+    ///
+    /// This plays the same role as Hetzner's `/records` endpoint for servers
+    /// that only speak RFC 1035/2136, letting `update_or_create_dns_record`
+    /// diff desired vs. actual state against a live zone. Reuses `connect()`
+    /// so the TSIG/connection setup only lives in one place.
+    ///
+    /// An AXFR response isn't guaranteed to fit in one DNS message, so this
+    /// drains the whole response stream rather than treating the first
+    /// message as the complete zone — otherwise a zone transfer that spans
+    /// more than one message would look truncated, and `reconcile` would
+    /// delete records that are still live on the server.
+    async fn list_dns_records(&self) -> Result<Vec<DnsRecord>, TransferError> {
+        let mut client = self.connect().await?;
+        let zone = self.apex()?;
+
+        let mut message = Message::new();
+        message.set_message_type(MessageType::Query);
+        message.set_op_code(OpCode::Query);
+        message.add_query(Query::query(zone, RecordType::AXFR));
+
+        let request = DnsRequest::new(message, DnsRequestOptions::default());
+        let mut response_stream = client.send(request);
+
+        let mut records = Vec::new();
+        while let Some(result) = response_stream.next().await {
+            let response = result.map_err(anyhow::Error::from)?;
+
+            match response.response_code() {
+                ResponseCode::NoError => {}
+                ResponseCode::Refused => return Err(TransferError::Refused),
+                code => return Err(anyhow::anyhow!("AXFR failed: {}", code).into()),
+            }
+
+            records.extend(
+                response
+                    .answers()
+                    .iter()
+                    .filter(|r| r.record_type() != RecordType::SOA)
+                    .map(|r| {
+                        let value = r.data().map(rdata_to_value).unwrap_or_default();
+                        DnsRecord {
+                            id: encode_id(&r.name().to_string(), &r.record_type().to_string(), &value),
+                            zone_id: self.config.zone.clone(),
+                            type_: r.record_type().to_string(),
+                            name: r.name().to_string(),
+                            value,
+                            ttl: r.ttl(),
+                        }
+                    }),
+            );
+        }
+
+        Ok(records)
+    }
+}
+
+#[async_trait]
+impl DnsProviderTrait for Rfc2136Dns {
+    /// Update or create a DNS record via an RFC 2136 dynamic update.
+    ///
+    /// Diffs against a fresh AXFR of the zone exactly the way the Hetzner
+    /// implementation diffs `existing_records`, then uses "RRset does not
+    /// exist" as a prerequisite when creating (so adding a second record
+    /// type at an already-used hostname doesn't get rejected), or "RRset
+    /// exists" when replacing an existing record.
+    async fn update_or_create_dns_record<'a>(
+        &self,
+        dns_record: &'a consul::DnsRecord,
+    ) -> Result<DnsRecord, anyhow::Error> {
+        let existing_records = self.list_dns_records().await?;
+
+        let matched_record = existing_records
+            .iter()
+            .find(|record| record.name == dns_record.hostname && record.type_ == dns_record.type_);
+
+        if let Some(record) = matched_record {
+            if record.value == dns_record.value && record.ttl == dns_record.ttl {
+                return Ok(record.clone());
+            }
+        }
+
+        let mut client = self.connect().await?;
+        let zone = self.apex()?;
+        let name = Name::from_str(&dns_record.hostname)?;
+        let record_type = RecordType::from_str(&dns_record.type_)
+            .map_err(|_| anyhow::anyhow!("unsupported record type: {}", dns_record.type_))?;
+
+        let new_rdata = rdata_from_value(record_type, &dns_record.value)?;
+        let mut new_record = Record::with(name.clone(), record_type, dns_record.ttl);
+        new_record.set_data(Some(new_rdata));
+
+        let response = match matched_record {
+            Some(existing) => {
+                // "RRset exists" prerequisite: it must describe the record's
+                // *current* rdata, not the value we're replacing it with.
+                let current_rdata = rdata_from_value(record_type, &existing.value)?;
+                let mut current_record = Record::with(name, record_type, existing.ttl);
+                current_record.set_data(Some(current_rdata));
+                client
+                    .compare_and_swap(current_record, new_record.clone(), zone)
+                    .await?
+            }
+            None => {
+                // "RRset does not exist": TYPE=record_type, CLASS=NONE,
+                // RDLENGTH=0 (§2.4.2). `client.create()`'s prerequisite
+                // covers the whole name ("name is not in use", §2.4.5),
+                // which would wrongly reject adding e.g. a TXT record at a
+                // hostname that already has an A record, so build the
+                // UPDATE by hand instead.
+                let mut prereq = Record::with(name.clone(), record_type, 0);
+                prereq.set_dns_class(DNSClass::NONE);
+
+                let mut message = Message::new();
+                message.set_message_type(MessageType::Query);
+                message.set_op_code(OpCode::Update);
+                message.add_query(Query::query(zone, RecordType::SOA));
+                message.add_answers(vec![prereq]);
+                message.add_name_servers(vec![new_record.clone()]);
+
+                let request = DnsRequest::new(message, DnsRequestOptions::default());
+                match client.send(request).next().await {
+                    Some(Ok(resp)) => resp,
+                    Some(Err(e)) => return Err(anyhow::Error::from(e)),
+                    None => return Err(anyhow::anyhow!("no response received for create")),
+                }
+            }
+        };
+
+        ensure_success(&response)?;
+
+        Ok(DnsRecord {
+            id: encode_id(&dns_record.hostname, &dns_record.type_, &dns_record.value),
+            zone_id: self.config.zone.clone(),
+            type_: dns_record.type_.clone(),
+            name: dns_record.hostname.clone(),
+            value: dns_record.value.clone(),
+            ttl: dns_record.ttl,
+        })
+    }
+
+    /// Delete a DNS record by issuing a delete-RRset update.
+    ///
+    /// `record_id` is expected to be the `name|type|value` id produced by
+    /// [`encode_id`], since DNS itself has no numeric record identifier.
+    async fn delete_dns_record<'a>(&self, record_id: &'a str) -> Result<(), anyhow::Error> {
+        let (name, type_, value) = decode_id(record_id)?;
+        let mut client = self.connect().await?;
+        let zone = self.apex()?;
+        let record_type = RecordType::from_str(&type_)
+            .map_err(|_| anyhow::anyhow!("unsupported record type: {}", type_))?;
+
+        let rdata = rdata_from_value(record_type, &value)?;
+        let mut record = Record::with(Name::from_str(&name)?, record_type, 0);
+        record.set_data(Some(rdata));
+
+        let response = client.delete_rrset(record, zone).await?;
+        ensure_success(&response)
+    }
+
+    /// Reconcile the whole zone against `desired` in a single atomic UPDATE
+    /// message, so the zone never passes through inconsistent intermediate
+    /// states the way it would issuing one UPDATE per record.
+    ///
+    /// Records present in `desired` but missing or stale on the server are
+    /// added via "RRset exists"/"RRset does not exist" prerequisites batched
+    /// into the same message, a stale record's current rdata being deleted
+    /// before its new rdata is added; records on the server that are no longer in
+    /// `desired` are deleted in the same message.
+    async fn reconcile(&self, desired: &[consul::DnsRecord]) -> Result<(), anyhow::Error> {
+        let existing_records = self.list_dns_records().await?;
+
+        // One prerequisite and its updates per name/type that needs to change,
+        // built from a raw Message rather than the single-RRset
+        // `compare_and_swap`/`create` helpers, so every addition and
+        // deletion below lands in one atomic UPDATE.
+        let mut prerequisites = Vec::new();
+        let mut updates = Vec::new();
+
+        for (dns_record, matched_record) in records_needing_write(&existing_records, desired) {
+            let record_type = RecordType::from_str(&dns_record.type_)
+                .map_err(|_| anyhow::anyhow!("unsupported record type: {}", dns_record.type_))?;
+            let name = Name::from_str(&dns_record.hostname)?;
+
+            let prereq = match matched_record {
+                Some(existing) => {
+                    // "RRset exists (value dependent)": describes the
+                    // record's *current* rdata, per RFC 2136 2.4.3.
+                    let current_rdata = rdata_from_value(record_type, &existing.value)?;
+                    let mut prereq = Record::with(name.clone(), record_type, 0);
+                    prereq.set_data(Some(current_rdata.clone()));
+
+                    // Delete the current rdata (CLASS=NONE, TTL=0) so the
+                    // add below replaces it instead of joining the RRset.
+                    let mut delete = Record::with(name.clone(), record_type, 0);
+                    delete.set_dns_class(DNSClass::NONE);
+                    delete.set_data(Some(current_rdata));
+                    updates.push(delete);
+
+                    prereq
+                }
+                None => {
+                    // "RRset does not exist": TYPE=record_type, CLASS=NONE,
+                    // RDLENGTH=0 (§2.4.2). Scoped to this type rather than
+                    // "name is not in use" (§2.4.5), so adding e.g. a TXT
+                    // record at a name that already has an A record isn't
+                    // rejected just because the name itself is in use.
+                    let mut prereq = Record::with(name.clone(), record_type, 0);
+                    prereq.set_dns_class(DNSClass::NONE);
+                    prereq
+                }
+            };
+
+            let new_rdata = rdata_from_value(record_type, &dns_record.value)?;
+            let mut update = Record::with(name, record_type, dns_record.ttl);
+            update.set_data(Some(new_rdata));
+
+            prerequisites.push(prereq);
+            updates.push(update);
+        }
+
+        for record in stale_records(&existing_records, desired) {
+            let record_type = RecordType::from_str(&record.type_)
+                .map_err(|_| anyhow::anyhow!("unsupported record type: {}", record.type_))?;
+            let name = Name::from_str(&record.name)?;
+
+            // "RRset exists (value independent)": TYPE matches, CLASS=ANY, RDLENGTH=0.
+            let mut prereq = Record::with(name.clone(), record_type, 0);
+            prereq.set_dns_class(DNSClass::ANY);
+            prerequisites.push(prereq);
+
+            // Delete RRset: TYPE matches, CLASS=ANY, TTL=0, RDLENGTH=0.
+            let mut delete = Record::with(name, record_type, 0);
+            delete.set_dns_class(DNSClass::ANY);
+            updates.push(delete);
+        }
+
+        if updates.is_empty() {
+            return Ok(());
+        }
+
+        let zone = self.apex()?;
+        let mut message = Message::new();
+        message.set_message_type(MessageType::Query);
+        message.set_op_code(OpCode::Update);
+        message.add_query(Query::query(zone, RecordType::SOA));
+        message.add_answers(prerequisites);
+        message.add_name_servers(updates);
+
+        let mut client = self.connect().await?;
+        let request = DnsRequest::new(message, DnsRequestOptions::default());
+        let response = match client.send(request).next().await {
+            Some(Ok(resp)) => resp,
+            Some(Err(e)) => return Err(anyhow::Error::from(e)),
+            None => return Err(anyhow::anyhow!("no response received for batched update")),
+        };
+
+        ensure_success(&response)
+    }
+}
+
+/// Errors specific to listing a zone via AXFR.
+#[derive(Debug)]
+pub enum TransferError {
+    /// The server refused the zone transfer, e.g. because the TSIG key
+    /// presented is not authorized for AXFR on this zone.
+    Refused,
+    Other(anyhow::Error),
+}
+
+impl std::fmt::Display for TransferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferError::Refused => write!(f, "zone transfer refused by server"),
+            TransferError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for TransferError {}
+
+impl From<anyhow::Error> for TransferError {
+    fn from(e: anyhow::Error) -> Self {
+        TransferError::Other(e)
+    }
+}
+
+/// Pair every `desired` record that is missing or stale against
+/// `existing_records` with the matching existing record, if any. Returning
+/// `None` for the match means "create"; returning `Some` means "replace".
+fn records_needing_write<'a>(
+    existing_records: &'a [DnsRecord],
+    desired: &'a [consul::DnsRecord],
+) -> Vec<(&'a consul::DnsRecord, Option<&'a DnsRecord>)> {
+    desired
+        .iter()
+        .filter_map(|dns_record| {
+            let matched_record = existing_records
+                .iter()
+                .find(|record| record.name == dns_record.hostname && record.type_ == dns_record.type_);
+            let needs_write = match matched_record {
+                Some(record) => record.value != dns_record.value || record.ttl != dns_record.ttl,
+                None => true,
+            };
+            needs_write.then_some((dns_record, matched_record))
+        })
+        .collect()
+}
+
+/// Records in `existing_records` whose name/type pair is no longer present
+/// in `desired`, and which should therefore be deleted.
+fn stale_records<'a>(
+    existing_records: &'a [DnsRecord],
+    desired: &[consul::DnsRecord],
+) -> Vec<&'a DnsRecord> {
+    existing_records
+        .iter()
+        .filter(|record| {
+            !desired
+                .iter()
+                .any(|dns_record| record.name == dns_record.hostname && record.type_ == dns_record.type_)
+        })
+        .collect()
+}
+
+fn rdata_to_value(rdata: &RData) -> String {
+    match rdata {
+        RData::A(addr) => addr.to_string(),
+        RData::AAAA(addr) => addr.to_string(),
+        RData::TXT(txt) => txt.to_string(),
+        RData::CNAME(name) => name.to_string(),
+        other => other.to_record_type().to_string(),
+    }
+}
+
+fn ensure_success(response: &DnsResponse) -> Result<(), anyhow::Error> {
+    match response.response_code() {
+        ResponseCode::NoError => Ok(()),
+        ResponseCode::Refused => Err(anyhow::anyhow!("update refused by server (TSIG rejected?)")),
+        code => Err(anyhow::anyhow!("update failed: {}", code)),
+    }
+}
+
+fn rdata_from_value(record_type: RecordType, value: &str) -> Result<RData, anyhow::Error> {
+    match record_type {
+        RecordType::A => Ok(RData::A(value.parse()?)),
+        RecordType::AAAA => Ok(RData::AAAA(value.parse()?)),
+        RecordType::TXT => Ok(RData::TXT(value.into())),
+        RecordType::CNAME => Ok(RData::CNAME(Name::from_str(value)?)),
+        other => Err(anyhow::anyhow!("unsupported record type: {}", other)),
+    }
+}
+
+/// Encode `name|type|value` as the record id, since DNS has no numeric id.
+fn encode_id(name: &str, type_: &str, value: &str) -> String {
+    format!("{}|{}|{}", name, type_, value)
+}
+
+fn decode_id(id: &str) -> Result<(String, String, String), anyhow::Error> {
+    let mut parts = id.splitn(3, '|');
+    let name = parts.next().ok_or_else(|| anyhow::anyhow!("invalid record id: {}", id))?;
+    let type_ = parts.next().ok_or_else(|| anyhow::anyhow!("invalid record id: {}", id))?;
+    let value = parts.next().ok_or_else(|| anyhow::anyhow!("invalid record id: {}", id))?;
+    Ok((name.to_string(), type_.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn existing(name: &str, type_: &str, value: &str, ttl: u32) -> DnsRecord {
+        DnsRecord {
+            id: encode_id(name, type_, value),
+            zone_id: "example.com".to_string(),
+            type_: type_.to_string(),
+            name: name.to_string(),
+            value: value.to_string(),
+            ttl,
+        }
+    }
+
+    fn desired(name: &str, type_: &str, value: &str, ttl: u32) -> consul::DnsRecord {
+        consul::DnsRecord {
+            hostname: name.to_string(),
+            type_: type_.to_string(),
+            value: value.to_string(),
+            ttl,
+        }
+    }
+
+    #[test]
+    fn records_needing_write_skips_unchanged_records() {
+        let existing_records = vec![existing("a.example.com", "A", "1.2.3.4", 300)];
+        let desired_records = vec![desired("a.example.com", "A", "1.2.3.4", 300)];
+
+        assert!(records_needing_write(&existing_records, &desired_records).is_empty());
+    }
+
+    #[test]
+    fn records_needing_write_flags_value_changes_as_a_replace() {
+        let existing_records = vec![existing("a.example.com", "A", "1.2.3.4", 300)];
+        let desired_records = vec![desired("a.example.com", "A", "5.6.7.8", 300)];
+
+        let needing_write = records_needing_write(&existing_records, &desired_records);
+        assert_eq!(needing_write.len(), 1);
+        let (record, matched) = needing_write[0];
+        assert_eq!(record.value, "5.6.7.8");
+        assert_eq!(matched.map(|r| r.value.as_str()), Some("1.2.3.4"));
+    }
+
+    #[test]
+    fn records_needing_write_flags_ttl_changes_as_a_replace() {
+        let existing_records = vec![existing("a.example.com", "A", "1.2.3.4", 300)];
+        let desired_records = vec![desired("a.example.com", "A", "1.2.3.4", 60)];
+
+        let needing_write = records_needing_write(&existing_records, &desired_records);
+        assert_eq!(needing_write.len(), 1);
+        assert!(needing_write[0].1.is_some());
+    }
+
+    #[test]
+    fn records_needing_write_flags_missing_records_as_a_create() {
+        let existing_records = vec![];
+        let desired_records = vec![desired("a.example.com", "A", "1.2.3.4", 300)];
+
+        let needing_write = records_needing_write(&existing_records, &desired_records);
+        assert_eq!(needing_write.len(), 1);
+        assert!(needing_write[0].1.is_none());
+    }
+
+    #[test]
+    fn stale_records_finds_records_absent_from_desired() {
+        let existing_records = vec![
+            existing("a.example.com", "A", "1.2.3.4", 300),
+            existing("b.example.com", "A", "1.2.3.4", 300),
+        ];
+        let desired_records = vec![desired("a.example.com", "A", "1.2.3.4", 300)];
+
+        let stale = stale_records(&existing_records, &desired_records);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].name, "b.example.com");
+    }
+
+    #[test]
+    fn stale_records_ignores_records_still_present_in_desired() {
+        let existing_records = vec![existing("a.example.com", "A", "1.2.3.4", 300)];
+        let desired_records = vec![desired("a.example.com", "A", "9.9.9.9", 60)];
+
+        assert!(stale_records(&existing_records, &desired_records).is_empty());
+    }
+}